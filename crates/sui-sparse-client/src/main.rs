@@ -7,7 +7,7 @@
 //  The  StreamUpdate  type represents a stream update.
 //  The  StreamUpdater  trait defines the  update  method that updates the sparse node with the given stream updates and returns the digest of the updated Merkle tree.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use sha2::{Sha256, Digest};
 
 #[derive(Debug, Clone)]
@@ -23,87 +23,1042 @@ pub struct StreamID(u32);
 /// The length of the digests used in the merkle tree.
 pub const DIGEST_LEN: usize = 32;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MyDigest([u8; DIGEST_LEN]); // A hash digest
 const EMPTY_NODE: [u8; DIGEST_LEN] = [0; DIGEST_LEN];
 
+/// A Merkle inclusion proof for a single leaf.
+///
+/// `siblings` lists the digest needed at each level to walk back up to the
+/// root, paired with whether that sibling sits to the left (`true`) or the
+/// right (`false`) of the node on our path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerkleProof {
+    pub leaf_index: usize,
+    pub siblings: Vec<(MyDigest, bool)>,
+}
+
+/// Domain-separation tag prepended before hashing leaf content, so a leaf digest can
+/// never be replayed as an internal node digest (and vice versa).
+pub const MERKLE_HASH_PREFIX_LEAF: u8 = 0x00;
+/// Domain-separation tag prepended before hashing a pair of child digests together.
+pub const MERKLE_HASH_PREFIX_NODE: u8 = 0x01;
+
+/// Hashes a leaf's raw bytes into the digest used as the bottom level of the tree.
+fn hash_leaf(bytes: &[u8]) -> MyDigest {
+    let mut hasher = Sha256::new();
+    hasher.update([MERKLE_HASH_PREFIX_LEAF]);
+    hasher.update(bytes);
+    MyDigest(hasher.finalize().into())
+}
+
+/// Hashes two child digests together into their parent.
+fn hash_pair(left: &MyDigest, right: &MyDigest) -> MyDigest {
+    let mut hasher = Sha256::new();
+    hasher.update([MERKLE_HASH_PREFIX_NODE]);
+    hasher.update(&left.0);
+    hasher.update(&right.0);
+    MyDigest(hasher.finalize().into())
+}
+
+/// Builds every level above `bottom`, pairing adjacent digests and promoting an odd
+/// node out unchanged, until a single root remains.
+fn build_levels_above(bottom: Vec<MyDigest>) -> Vec<Vec<MyDigest>> {
+    let mut levels = Vec::new();
+    let mut current = bottom;
+    levels.push(current.clone());
+
+    while current.len() > 1 {
+        let mut next = Vec::with_capacity((current.len() + 1) / 2);
+        for pair in current.chunks(2) {
+            if pair.len() == 2 {
+                next.push(hash_pair(&pair[0], &pair[1]));
+            } else {
+                next.push(pair[0].clone());
+            }
+        }
+        levels.push(next.clone());
+        current = next;
+    }
+    levels
+}
+
+/// Builds every level of a binary Merkle tree over `leaves`, bottom (leaf
+/// digests) first and the single-element root level last. An odd node out at
+/// any level is promoted unchanged to the level above.
+fn build_merkle_levels(leaves: &[Leaf]) -> Vec<Vec<MyDigest>> {
+    let mut bottom: Vec<MyDigest> = leaves.iter().map(|leaf| hash_leaf(&leaf.0)).collect();
+    if bottom.is_empty() {
+        bottom.push(hash_leaf(&[]));
+    }
+    build_levels_above(bottom)
+}
+
+/// Extracts the inclusion proof for `leaf_index` out of the precomputed tree levels.
+fn merkle_proof_from_levels(levels: &[Vec<MyDigest>], leaf_index: usize) -> MerkleProof {
+    let mut siblings = Vec::new();
+    let mut index = leaf_index;
+    for level in &levels[..levels.len() - 1] {
+        let sibling_index = index ^ 1;
+        if let Some(sibling) = level.get(sibling_index) {
+            siblings.push((sibling.clone(), sibling_index < index));
+        }
+        index /= 2;
+    }
+    MerkleProof { leaf_index, siblings }
+}
+
+/// Recomputes the root implied by `leaf` and `proof` and checks it matches `root`.
+pub fn verify(root: &MyDigest, leaf: &Leaf, proof: &MerkleProof) -> bool {
+    let mut digest = hash_leaf(&leaf.0);
+    for (sibling, sibling_is_left) in &proof.siblings {
+        digest = if *sibling_is_left {
+            hash_pair(sibling, &digest)
+        } else {
+            hash_pair(&digest, sibling)
+        };
+    }
+    digest == *root
+}
+
 // TODO: Enhance it to support transactions for sparse nodes that re-executes the updates
 pub struct Point([u8; DIGEST_LEN]); // Either an effects digest or a event digest
 const EMPTY_POINT: Point = Point(EMPTY_NODE);
 
 pub type StreamUpdate = (StreamID, Vec<Point>); // (stream_id, [point_1, point_2, ..., point_n])
 
+/// One instruction in a batch passed to `StreamUpdater::apply`: either append points
+/// to a stream, or just read its current state without changing it.
+pub enum TreeInstruction {
+    Write(StreamID, Vec<Point>),
+    Read(StreamID),
+}
+
 pub trait StreamUpdater {
     fn update(&mut self, updates: Vec<StreamUpdate>) -> MerkleTreeDigest;
+
+    /// Proves whether `stream_id` is committed to by the digest returned by the most
+    /// recent `update`/`apply` call. Returns `None` if this node can't produce a
+    /// proof for `stream_id` (e.g. it has never been touched, and this
+    /// implementation doesn't support non-membership proofs).
+    fn prove(&self, stream_id: StreamID) -> Option<MerkleProof>;
+
+    /// Applies a batch of reads and writes in a single pass: each `Read` is proved
+    /// against the state before this batch, each `Write` applies its points and is
+    /// then proved against the state after the whole batch, and the returned digest
+    /// is the post-batch root. Proofs are returned in the same order as the
+    /// instructions that produced them, `None` wherever `prove` can't produce one
+    /// (e.g. a `Read` of a stream this node has never touched and can't prove absent).
+    fn apply(&mut self, instructions: Vec<TreeInstruction>) -> (MerkleTreeDigest, Vec<Option<MerkleProof>>) {
+        let mut proofs: Vec<Option<MerkleProof>> = Vec::with_capacity(instructions.len());
+        let mut writes = Vec::new();
+        let mut write_positions = Vec::new();
+
+        for instruction in instructions {
+            match instruction {
+                TreeInstruction::Read(stream_id) => proofs.push(self.prove(stream_id)),
+                TreeInstruction::Write(stream_id, points) => {
+                    write_positions.push((proofs.len(), stream_id));
+                    proofs.push(None); // filled in below, once `update` has run
+                    writes.push((stream_id, points));
+                }
+            }
+        }
+
+        let digest = self.update(writes);
+
+        for (position, stream_id) in write_positions {
+            proofs[position] = self.prove(stream_id);
+        }
+
+        (digest, proofs)
+    }
 }
 
 pub fn compute_merkle_tree(digests: Vec<Leaf>) -> MerkleTreeDigest {
-    let mut hasher = Sha256::new();
-    for digest in digests {
-        hasher.update(&digest.0);
-    }
-    MerkleTreeDigest(hasher.finalize().to_vec())
+    let levels = build_merkle_levels(&digests);
+    let root = &levels.last().unwrap()[0];
+    MerkleTreeDigest(root.0.to_vec())
+}
+
+/// A pluggable key-value backend for per-stream state, so a sparse node's data can
+/// survive a process restart instead of living only in a `HashMap`.
+pub trait Store<V: Clone> {
+    fn get(&self, id: StreamID) -> Option<V>;
+    fn set(&mut self, id: StreamID, value: V);
+    /// Drains and returns the entries written since the last call, i.e. the leaves
+    /// touched by the batch currently being applied.
+    fn iter_updated(&mut self) -> Vec<(StreamID, V)>;
 }
 
-pub struct CounterSparseNode {
-    // TODO: Change HashMap to a DB
-    pub counts: HashMap<StreamID, u32>,
+/// Keeps all state in a `HashMap`; the default `Store` for tests and short-lived nodes.
+#[derive(Default)]
+pub struct InMemoryStore<V> {
+    values: HashMap<StreamID, V>,
+    updated: Vec<StreamID>,
 }
 
-impl CounterSparseNode {
+impl<V> InMemoryStore<V> {
     pub fn new() -> Self {
-        Self { counts: HashMap::new() }
+        Self { values: HashMap::new(), updated: Vec::new() }
+    }
+}
+
+impl<V: Clone> Store<V> for InMemoryStore<V> {
+    fn get(&self, id: StreamID) -> Option<V> {
+        self.values.get(&id).cloned()
+    }
+
+    fn set(&mut self, id: StreamID, value: V) {
+        self.values.insert(id, value);
+        self.updated.push(id);
+    }
+
+    fn iter_updated(&mut self) -> Vec<(StreamID, V)> {
+        self.updated
+            .drain(..)
+            .map(|id| (id, self.values[&id].clone()))
+            .collect()
+    }
+}
+
+/// An open transaction's buffered mutations, generic over the value type a sparse
+/// node's `Store` holds (`u32` counters, `MyDigest` chain heads, ...). `overlay`
+/// holds each touched stream's tentative value, so `update` can keep re-executing
+/// against it without writing through to the store; `deltas` holds the value each
+/// touched stream held before the transaction began (`None` if it didn't exist yet),
+/// so `rollback` can put it back.
+struct PendingTransaction<V> {
+    overlay: HashMap<StreamID, V>,
+    deltas: HashMap<StreamID, Option<V>>,
+}
+
+impl<V> Default for PendingTransaction<V> {
+    fn default() -> Self {
+        PendingTransaction {
+            overlay: HashMap::new(),
+            deltas: HashMap::new(),
+        }
+    }
+}
+
+/// Values a `Store` can persist directly as column bytes.
+pub trait StoreValue: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+
+impl StoreValue for u32 {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        u32::from_be_bytes(bytes.try_into().expect("u32 column value must be 4 bytes"))
+    }
+}
+
+impl StoreValue for MyDigest {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        MyDigest(bytes.try_into().expect("digest column value must be 32 bytes"))
+    }
+}
+
+/// A `Store` backed by a RocksDB column family, keyed by the stream id's big-endian bytes, so a
+/// node can reload its counters or heads and resume `update` after a crash without
+/// replaying every point.
+///
+/// This crate is a source snapshot with no `Cargo.toml`, so the `rocksdb` dependency and
+/// feature this gate assumes can't be wired up here. It was exercised (module and
+/// `test_rocksdb_store_survives_reopen` below, behind `--features rocksdb`) against a
+/// throwaway manifest pulling in `rocksdb = "0.22"`; that build fails before it reaches
+/// this code, because `rocksdb`'s `bindgen` build step needs `libclang` and this sandbox
+/// has no `libclang.so` (only `libclang-cpp.so.14`) and no network to install one. Landing
+/// a manifest here would still leave the feature unbuildable in this environment -- the
+/// blocker is the missing `libclang`, not the absence of a `Cargo.toml`.
+#[cfg(feature = "rocksdb")]
+pub struct RocksDbStore<V> {
+    db: rocksdb::DB,
+    updated: Vec<StreamID>,
+    _value: std::marker::PhantomData<V>,
+}
+
+#[cfg(feature = "rocksdb")]
+impl<V> RocksDbStore<V> {
+    pub fn open(path: &std::path::Path) -> Result<Self, rocksdb::Error> {
+        Ok(Self {
+            db: rocksdb::DB::open_default(path)?,
+            updated: Vec::new(),
+            _value: std::marker::PhantomData,
+        })
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl<V: StoreValue + Clone> Store<V> for RocksDbStore<V> {
+    fn get(&self, id: StreamID) -> Option<V> {
+        self.db
+            .get(id.0.to_be_bytes())
+            .expect("rocksdb get failed")
+            .map(|bytes| V::from_bytes(&bytes))
+    }
+
+    fn set(&mut self, id: StreamID, value: V) {
+        self.db
+            .put(id.0.to_be_bytes(), value.to_bytes())
+            .expect("rocksdb put failed");
+        self.updated.push(id);
+    }
+
+    fn iter_updated(&mut self) -> Vec<(StreamID, V)> {
+        let ids: Vec<StreamID> = self.updated.drain(..).collect();
+        ids.into_iter()
+            .map(|id| {
+                let value = self.get(id).expect("entry just written must be readable");
+                (id, value)
+            })
+            .collect()
+    }
+}
+
+pub struct CounterSparseNode<S: Store<u32>> {
+    store: S,
+    // The size of the most recent update batch seen by each stream, i.e. the
+    // `local_count` baked into its leaf; unchanged for streams not touched this round.
+    last_deltas: HashMap<StreamID, u32>,
+    // The Merkle tree built over `store` as of the last `update` call, kept around so
+    // `prove` can hand out an inclusion proof without recomputing the whole tree.
+    tree_levels: Vec<Vec<MyDigest>>,
+    leaf_indices: HashMap<StreamID, usize>,
+    // Set while a transaction is open; see `begin`. `last_deltas` is snapshotted
+    // alongside it since `update` mutates that map unconditionally too.
+    transaction: Option<(PendingTransaction<u32>, HashMap<StreamID, u32>)>,
+}
+
+impl<S: Store<u32>> CounterSparseNode<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            last_deltas: HashMap::new(),
+            tree_levels: Vec::new(),
+            leaf_indices: HashMap::new(),
+            transaction: None,
+        }
+    }
+
+    /// The current counter for `stream_id`, if it has ever been touched, including
+    /// any tentative value buffered by an open transaction.
+    pub fn count(&self, stream_id: StreamID) -> Option<u32> {
+        self.current_count(stream_id)
+    }
+
+    fn current_count(&self, stream_id: StreamID) -> Option<u32> {
+        if let Some((tx, _)) = &self.transaction {
+            if let Some(value) = tx.overlay.get(&stream_id) {
+                return Some(*value);
+            }
+        }
+        self.store.get(stream_id)
+    }
+
+    fn set_count(&mut self, stream_id: StreamID, value: u32) {
+        match &mut self.transaction {
+            Some(_) => {
+                let original = self.store.get(stream_id);
+                let (tx, _) = self.transaction.as_mut().unwrap();
+                tx.deltas.entry(stream_id).or_insert(original);
+                tx.overlay.insert(stream_id, value);
+            }
+            None => self.store.set(stream_id, value),
+        }
+    }
+
+    /// Opens a transaction: until `commit` or `rollback`, `update` buffers its
+    /// writes instead of writing them through to the store, so a caller can
+    /// speculatively re-execute a batch, inspect `root`, and abandon it cheaply if
+    /// it isn't the root they expected.
+    pub fn begin(&mut self) {
+        self.transaction = Some((PendingTransaction::default(), self.last_deltas.clone()));
+    }
+
+    /// The tentative root if the open transaction were committed right now, without
+    /// writing anything through to the store.
+    pub fn root(&mut self) -> MerkleTreeDigest {
+        assert!(self.transaction.is_some(), "root: no open transaction");
+        self.update(Vec::new())
+    }
+
+    /// Writes the open transaction's buffered values through to the store.
+    pub fn commit(&mut self) {
+        let (tx, _) = self.transaction.take().expect("commit: no open transaction");
+        for (stream_id, value) in tx.overlay {
+            self.store.set(stream_id, value);
+        }
+    }
+
+    /// Discards the open transaction, restoring every touched stream (and
+    /// `last_deltas`) to what it held before `begin`.
+    pub fn rollback(&mut self) {
+        let (tx, last_deltas) = self.transaction.take().expect("rollback: no open transaction");
+        for (stream_id, value) in tx.deltas {
+            match value {
+                Some(value) => self.store.set(stream_id, value),
+                // Never existed before this transaction, so it must not linger in the
+                // tree `update` rebuilds below.
+                None => {
+                    self.leaf_indices.remove(&stream_id);
+                }
+            }
+        }
+        self.last_deltas = last_deltas;
+        self.update(Vec::new());
+    }
+
+    /// Leaf bytes committed to the tree for `stream_id`, i.e. [id, local_count, global_count];
+    /// the tree hashes them (with the leaf tag) into this stream's leaf digest.
+    fn leaf_bytes(stream_id: StreamID, sum: u32, counter: u32) -> Leaf {
+        let mut bytes = Vec::with_capacity(4 + 4 + 4);
+        bytes.extend_from_slice(&stream_id.0.to_be_bytes());
+        bytes.extend_from_slice(&sum.to_be_bytes());
+        bytes.extend_from_slice(&counter.to_be_bytes());
+        Leaf(bytes)
+    }
+
+    /// Produces an inclusion proof for `stream_id` against the root returned by the
+    /// most recent `update`, or `None` if the stream hasn't been touched yet.
+    pub fn prove(&self, stream_id: StreamID) -> Option<MerkleProof> {
+        let leaf_index = *self.leaf_indices.get(&stream_id)?;
+        Some(merkle_proof_from_levels(&self.tree_levels, leaf_index))
     }
 }
 
-impl StreamUpdater for CounterSparseNode {
+impl<S: Store<u32>> StreamUpdater for CounterSparseNode<S> {
+    fn prove(&self, stream_id: StreamID) -> Option<MerkleProof> {
+        self.prove(stream_id)
+    }
+
     // Computes H([id, local_count, global_count])
     fn update(&mut self, updates: Vec<StreamUpdate>) -> MerkleTreeDigest {
-        let mut leafs = Vec::new();
         for (stream_id, points) in updates {
             let sum: u32 = points.len().try_into().unwrap();
-            let counter = self.counts.entry(stream_id).or_insert(0);
-            *counter += sum;
+            let counter = self.current_count(stream_id).unwrap_or(0) + sum;
+            self.set_count(stream_id, counter);
+            self.last_deltas.insert(stream_id, sum);
+        }
 
-            // Only hash the updated entries
-            let mut hasher = Sha256::new();
-            hasher.update(&stream_id.0.to_be_bytes());
-            hasher.update(&sum.to_be_bytes());
-            hasher.update(&counter.to_be_bytes());
-            let digest = hasher.finalize();
-            leafs.push(Leaf(digest.to_vec()));
+        // Every stream touched this batch, plus every stream already indexed from a
+        // previous batch, so a proof for any previously-updated stream still works.
+        let mut seen: HashSet<StreamID> = self.leaf_indices.keys().copied().collect();
+        let mut stream_ids: Vec<StreamID> = seen.iter().copied().collect();
+        for (id, _) in self.store.iter_updated() {
+            if seen.insert(id) {
+                stream_ids.push(id);
+            }
+        }
+        // A transaction buffers its writes instead of writing through to the store,
+        // so a stream touched only inside it never shows up in `store.iter_updated`.
+        if let Some((tx, _)) = &self.transaction {
+            for id in tx.overlay.keys() {
+                if seen.insert(*id) {
+                    stream_ids.push(*id);
+                }
+            }
         }
+        stream_ids.sort_by_key(|id| id.0);
 
-        // Finalize the digest with only the updated entries
-        compute_merkle_tree(leafs)
+        let leafs: Vec<Leaf> = stream_ids
+            .iter()
+            .map(|id| {
+                let counter = self.current_count(*id).expect("known stream must be in the store");
+                let delta = *self.last_deltas.get(id).unwrap_or(&0);
+                Self::leaf_bytes(*id, delta, counter)
+            })
+            .collect();
+
+        self.leaf_indices = stream_ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id, index))
+            .collect();
+        self.tree_levels = build_merkle_levels(&leafs);
+
+        let root = &self.tree_levels.last().unwrap()[0];
+        MerkleTreeDigest(root.0.to_vec())
     }
 }
 
-pub struct HashChainSparseNode {
-    pub heads: HashMap<StreamID, MyDigest >
+pub struct HashChainSparseNode<S: Store<MyDigest>> {
+    store: S,
+    // The Merkle tree built over `store` as of the last `update` call, kept around so
+    // `prove` can hand out an inclusion proof without recomputing the whole tree.
+    tree_levels: Vec<Vec<MyDigest>>,
+    leaf_indices: HashMap<StreamID, usize>,
+    // Set while a transaction is open; see `begin`.
+    transaction: Option<PendingTransaction<MyDigest>>,
 }
 
-impl HashChainSparseNode {
-    pub fn new() -> Self {
-        Self { heads: HashMap::new() }
+impl<S: Store<MyDigest>> HashChainSparseNode<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            tree_levels: Vec::new(),
+            leaf_indices: HashMap::new(),
+            transaction: None,
+        }
+    }
+
+    /// The current chain head for `stream_id`, if it has ever been touched,
+    /// including any tentative value buffered by an open transaction.
+    pub fn head(&self, stream_id: StreamID) -> Option<MyDigest> {
+        self.current_head(stream_id)
+    }
+
+    fn current_head(&self, stream_id: StreamID) -> Option<MyDigest> {
+        if let Some(tx) = &self.transaction {
+            if let Some(value) = tx.overlay.get(&stream_id) {
+                return Some(value.clone());
+            }
+        }
+        self.store.get(stream_id)
+    }
+
+    fn set_head(&mut self, stream_id: StreamID, value: MyDigest) {
+        match &mut self.transaction {
+            Some(_) => {
+                let original = self.store.get(stream_id);
+                let tx = self.transaction.as_mut().unwrap();
+                tx.deltas.entry(stream_id).or_insert(original);
+                tx.overlay.insert(stream_id, value);
+            }
+            None => self.store.set(stream_id, value),
+        }
+    }
+
+    /// Opens a transaction: until `commit` or `rollback`, `update` buffers its
+    /// writes instead of writing them through to the store, so a caller can
+    /// speculatively re-execute a batch, inspect `root`, and abandon it cheaply if
+    /// it isn't the root they expected.
+    pub fn begin(&mut self) {
+        self.transaction = Some(PendingTransaction::default());
+    }
+
+    /// The tentative root if the open transaction were committed right now, without
+    /// writing anything through to the store.
+    pub fn root(&mut self) -> MerkleTreeDigest {
+        assert!(self.transaction.is_some(), "root: no open transaction");
+        self.update(Vec::new())
+    }
+
+    /// Writes the open transaction's buffered values through to the store.
+    pub fn commit(&mut self) {
+        let tx = self.transaction.take().expect("commit: no open transaction");
+        for (stream_id, value) in tx.overlay {
+            self.store.set(stream_id, value);
+        }
+    }
+
+    /// Discards the open transaction, restoring every touched stream to what it
+    /// held before `begin`.
+    pub fn rollback(&mut self) {
+        let tx = self.transaction.take().expect("rollback: no open transaction");
+        for (stream_id, value) in tx.deltas {
+            match value {
+                Some(value) => self.store.set(stream_id, value),
+                // Never existed before this transaction, so it must not linger in the
+                // tree `update` rebuilds below.
+                None => {
+                    self.leaf_indices.remove(&stream_id);
+                }
+            }
+        }
+        self.update(Vec::new());
+    }
+
+    /// Produces an inclusion proof for `stream_id` against the root returned by the
+    /// most recent `update`, or `None` if the stream hasn't been touched yet.
+    pub fn prove(&self, stream_id: StreamID) -> Option<MerkleProof> {
+        let leaf_index = *self.leaf_indices.get(&stream_id)?;
+        Some(merkle_proof_from_levels(&self.tree_levels, leaf_index))
     }
 }
 
-impl StreamUpdater for HashChainSparseNode {
+impl<S: Store<MyDigest>> StreamUpdater for HashChainSparseNode<S> {
+    fn prove(&self, stream_id: StreamID) -> Option<MerkleProof> {
+        self.prove(stream_id)
+    }
+
     fn update(&mut self, updates: Vec<StreamUpdate>) -> MerkleTreeDigest {
-        let mut leafs: Vec<Leaf> = Vec::new();
         for (stream_id, points) in updates {
-            let head = self.heads.entry(stream_id).or_insert(MyDigest([0; 32]));
+            let mut head = self.current_head(stream_id).unwrap_or(MyDigest([0; 32]));
             for point in points {
                 let mut hasher = Sha256::new();
+                hasher.update([MERKLE_HASH_PREFIX_LEAF]);
                 hasher.update(&head.0);
                 hasher.update(&point.0);
                 let digest = hasher.finalize();
-                *head = MyDigest(digest.into());
+                head = MyDigest(digest.into());
+            }
+            self.set_head(stream_id, head);
+        }
+
+        // Every stream touched this batch, plus every stream already indexed from a
+        // previous batch, so a proof for any previously-updated stream still works.
+        let mut seen: HashSet<StreamID> = self.leaf_indices.keys().copied().collect();
+        let mut stream_ids: Vec<StreamID> = seen.iter().copied().collect();
+        for (id, _) in self.store.iter_updated() {
+            if seen.insert(id) {
+                stream_ids.push(id);
             }
-            leafs.push(Leaf(head.0.to_vec()));
         }
-        return compute_merkle_tree(leafs)
+        // A transaction buffers its writes instead of writing through to the store,
+        // so a stream touched only inside it never shows up in `store.iter_updated`.
+        if let Some(tx) = &self.transaction {
+            for id in tx.overlay.keys() {
+                if seen.insert(*id) {
+                    stream_ids.push(*id);
+                }
+            }
+        }
+        stream_ids.sort_by_key(|id| id.0);
+
+        let leafs: Vec<Leaf> = stream_ids
+            .iter()
+            .map(|id| {
+                let head = self.current_head(*id).expect("known stream must be in the store");
+                Leaf(head.0.to_vec())
+            })
+            .collect();
+
+        self.leaf_indices = stream_ids
+            .iter()
+            .enumerate()
+            .map(|(index, id)| (*id, index))
+            .collect();
+        self.tree_levels = build_merkle_levels(&leafs);
+
+        let root = &self.tree_levels.last().unwrap()[0];
+        MerkleTreeDigest(root.0.to_vec())
+    }
+}
+
+/// Precomputes `empty_subtrees[d]`, the root of an empty subtree of height `d` for
+/// every `d` in `0..=depth`, so an unfilled subtree folds in as a constant instead of
+/// being rehashed from scratch.
+fn build_empty_subtrees(depth: usize) -> Vec<MyDigest> {
+    let mut empty_subtrees = Vec::with_capacity(depth + 1);
+    empty_subtrees.push(MyDigest(EMPTY_NODE));
+    for level in 0..depth {
+        let prev = empty_subtrees[level].clone();
+        empty_subtrees.push(hash_pair(&prev, &prev));
+    }
+    empty_subtrees
+}
+
+/// The depth of the append-only subtree `IncrementalSparseNode` gives each stream,
+/// i.e. up to `2^32` points per stream.
+pub const DEFAULT_INCREMENTAL_DEPTH: usize = 32;
+
+/// An authentication path for one leaf of an append-only subtree, kept current as
+/// `IncrementalSparseNode::update` appends more points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Witness {
+    pub leaf_index: u64,
+    pub siblings: Vec<MyDigest>,
+}
+
+/// Recomputes the root implied by `leaf` and `witness` over a depth-`depth` append-only
+/// subtree and checks it matches `root`.
+pub fn verify_witness(root: &MyDigest, leaf: &Leaf, witness: &Witness, depth: usize) -> bool {
+    let mut digest = hash_leaf(&leaf.0);
+    let mut idx = witness.leaf_index;
+    for level in 0..depth {
+        let sibling = &witness.siblings[level];
+        digest = if idx % 2 == 0 {
+            hash_pair(&digest, sibling)
+        } else {
+            hash_pair(sibling, &digest)
+        };
+        idx /= 2;
+    }
+    digest == *root
+}
+
+/// One stream's append-only subtree. Only the "frontier" — the left sibling at each
+/// level still waiting for a right neighbor — is kept; everything else is discarded
+/// once it has been folded into the root.
+struct StreamTree {
+    next_leaf: u64,
+    frontier: Vec<Option<MyDigest>>,
+    root: MyDigest,
+    witness: Option<Witness>,
+}
+
+impl StreamTree {
+    fn new(depth: usize, empty_subtree_root: MyDigest) -> Self {
+        Self {
+            next_leaf: 0,
+            frontier: vec![None; depth],
+            root: empty_subtree_root,
+            witness: None,
+        }
+    }
+}
+
+/// Gives each `StreamID` a fixed-depth, append-only Merkle subtree and, on request,
+/// maintains a witness (authentication path) for that stream's most recently
+/// appended leaf across subsequent `update` calls — so a subscriber never has to
+/// re-derive a proof from scratch as new points arrive.
+pub struct IncrementalSparseNode {
+    depth: usize,
+    // empty_subtrees[d] is the root of an empty subtree of height d, precomputed once
+    // so an unfilled right child folds in as a constant instead of being rehashed.
+    empty_subtrees: Vec<MyDigest>,
+    trees: HashMap<StreamID, StreamTree>,
+    // The tree combining every stream's subtree root, built over from the last
+    // `update` call, kept around so `prove` can append this outer tree's siblings
+    // to a witness without recomputing the whole thing.
+    tree_levels: Vec<Vec<MyDigest>>,
+    leaf_indices: HashMap<StreamID, usize>,
+}
+
+impl IncrementalSparseNode {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            empty_subtrees: build_empty_subtrees(depth),
+            trees: HashMap::new(),
+            tree_levels: Vec::new(),
+            leaf_indices: HashMap::new(),
+        }
+    }
+
+    /// Marks `stream_id`'s most recently appended leaf so its authentication path is
+    /// kept current as more points arrive, and returns that starting witness.
+    /// Returns `None` if the stream has never been appended to.
+    pub fn witness(&mut self, stream_id: StreamID) -> Option<Witness> {
+        let empty_subtrees = &self.empty_subtrees;
+        let tree = self.trees.get_mut(&stream_id)?;
+        if tree.next_leaf == 0 {
+            return None;
+        }
+        let leaf_index = tree.next_leaf - 1;
+        let witness = Witness {
+            leaf_index,
+            siblings: Self::initial_siblings(leaf_index, &tree.frontier, tree.next_leaf, empty_subtrees),
+        };
+        tree.witness = Some(witness.clone());
+        Some(witness)
+    }
+
+    /// The witness most recently started by `witness` for `stream_id`, advanced by
+    /// every `update` call since, or `None` if none was requested for this stream.
+    pub fn get_witness(&self, stream_id: StreamID) -> Option<Witness> {
+        self.trees.get(&stream_id)?.witness.clone()
+    }
+
+    /// The root of `stream_id`'s own append-only subtree.
+    pub fn root(&self, stream_id: StreamID) -> Option<MyDigest> {
+        self.trees.get(&stream_id).map(|tree| tree.root.clone())
+    }
+
+    /// Folds `frontier` and the precomputed empty-subtree constants into the root of
+    /// a depth-`depth` tree containing `leaf_count` leaves, treating every position
+    /// from `leaf_count` onward as empty.
+    fn current_root(frontier: &[Option<MyDigest>], leaf_count: u64, empty_subtrees: &[MyDigest]) -> MyDigest {
+        let mut node = empty_subtrees[0].clone();
+        for (level, empty_subtree) in empty_subtrees.iter().enumerate().take(frontier.len()) {
+            node = if (leaf_count >> level) & 1 == 1 {
+                hash_pair(frontier[level].as_ref().unwrap(), &node)
+            } else {
+                hash_pair(&node, empty_subtree)
+            };
+        }
+        node
+    }
+
+    /// Keeps `witness`'s sibling at each level in sync with the subtree its
+    /// witnessed leaf's ancestor is still waiting to be completed by -- a level
+    /// whose right neighbor hasn't closed off yet changes digest every time a new
+    /// point lands in it, so it can't just be captured once like a closed one can.
+    /// (A sibling whose subtree already closed was captured for good inside the
+    /// append loop of `update`, at the moment it closed, and is left untouched here.)
+    fn refresh_open_sibling(
+        witness: &mut Witness,
+        frontier: &[Option<MyDigest>],
+        leaf_count: u64,
+        empty_subtrees: &[MyDigest],
+    ) {
+        let mut node = empty_subtrees[0].clone();
+        for level in 0..frontier.len() {
+            let open_ancestor = leaf_count >> level;
+            let sibling_ancestor = (witness.leaf_index >> level) ^ 1;
+            if open_ancestor == sibling_ancestor {
+                witness.siblings[level] = node.clone();
+            }
+            node = if (leaf_count >> level) & 1 == 1 {
+                hash_pair(frontier[level].as_ref().unwrap(), &node)
+            } else {
+                hash_pair(&node, &empty_subtrees[level])
+            };
+        }
+    }
+
+    /// Builds the authentication path `leaf_index` needs right now, for a stream
+    /// whose frontier already holds `leaf_count` leaves -- unlike `refresh_open_sibling`,
+    /// this has no previously-captured siblings to fall back on, so it classifies
+    /// every level itself: the witnessed leaf's sibling subtree at that level is either
+    /// the one still being filled (tracked by `node`, same fold as `current_root`),
+    /// one that's already closed and dangling in `frontier`, or one that hasn't been
+    /// touched at all yet (the precomputed empty constant).
+    fn initial_siblings(
+        leaf_index: u64,
+        frontier: &[Option<MyDigest>],
+        leaf_count: u64,
+        empty_subtrees: &[MyDigest],
+    ) -> Vec<MyDigest> {
+        let mut node = empty_subtrees[0].clone();
+        let mut siblings = Vec::with_capacity(frontier.len());
+        for level in 0..frontier.len() {
+            let open_ancestor = leaf_count >> level;
+            let sibling_ancestor = (leaf_index >> level) ^ 1;
+            siblings.push(if sibling_ancestor == open_ancestor {
+                node.clone()
+            } else if sibling_ancestor < open_ancestor {
+                frontier[level].clone().expect("a closed sibling subtree must be in the frontier")
+            } else {
+                empty_subtrees[level].clone()
+            });
+            node = if (leaf_count >> level) & 1 == 1 {
+                hash_pair(frontier[level].as_ref().unwrap(), &node)
+            } else {
+                hash_pair(&node, &empty_subtrees[level])
+            };
+        }
+        siblings
+    }
+}
+
+impl StreamUpdater for IncrementalSparseNode {
+    // Only a stream that's had `witness` called for it can be proved this way. The
+    // witness only chains up to that stream's own subtree root, so its siblings are
+    // extended with the outer combination tree's siblings for that stream's position,
+    // to chain the rest of the way to the digest `update` actually returns.
+    fn prove(&self, stream_id: StreamID) -> Option<MerkleProof> {
+        let witness = self.get_witness(stream_id)?;
+        let mut siblings: Vec<(MyDigest, bool)> = witness
+            .siblings
+            .iter()
+            .enumerate()
+            .map(|(level, sibling)| {
+                let sibling_is_left = (witness.leaf_index >> level) & 1 == 1;
+                (sibling.clone(), sibling_is_left)
+            })
+            .collect();
+        let outer_index = *self.leaf_indices.get(&stream_id)?;
+        siblings.extend(merkle_proof_from_levels(&self.tree_levels, outer_index).siblings);
+        Some(MerkleProof { leaf_index: witness.leaf_index as usize, siblings })
+    }
+
+    fn update(&mut self, updates: Vec<StreamUpdate>) -> MerkleTreeDigest {
+        for (stream_id, points) in updates {
+            let depth = self.depth;
+            let empty_root = self.empty_subtrees[depth].clone();
+            let tree = self
+                .trees
+                .entry(stream_id)
+                .or_insert_with(|| StreamTree::new(depth, empty_root));
+
+            for point in points {
+                let leaf_index = tree.next_leaf;
+                assert!(
+                    leaf_index < (1u64 << depth),
+                    "stream {:?} exceeded the tree's depth of {}",
+                    stream_id,
+                    depth
+                );
+
+                // The new leaf's position, viewed in binary, tells us exactly which
+                // levels it completes a pair at (the set bits of `leaf_index`) versus
+                // which level it's left dangling at, waiting for a future right
+                // neighbor (the first unset bit) -- the same trick a binary counter
+                // uses to decide how far a carry propagates.
+                let mut node = hash_leaf(&point.0);
+                for level in 0..depth {
+                    if (leaf_index >> level) & 1 == 1 {
+                        if let Some(witness) = tree.witness.as_mut() {
+                            if (witness.leaf_index >> level) == ((leaf_index >> level) ^ 1) {
+                                witness.siblings[level] = node.clone();
+                            }
+                        }
+                        let left = tree.frontier[level]
+                            .take()
+                            .expect("a set bit means this level's left sibling was already stashed");
+                        node = hash_pair(&left, &node);
+                    } else {
+                        tree.frontier[level] = Some(node.clone());
+                        break;
+                    }
+                }
+                tree.next_leaf += 1;
+                tree.root = if tree.next_leaf == 1u64 << depth {
+                    // Every level carried, so `node` is the root of the now-full subtree.
+                    node
+                } else {
+                    Self::current_root(&tree.frontier, tree.next_leaf, &self.empty_subtrees)
+                };
+                if let Some(witness) = tree.witness.as_mut() {
+                    Self::refresh_open_sibling(witness, &tree.frontier, tree.next_leaf, &self.empty_subtrees);
+                }
+            }
+        }
+
+        // Combine every stream's subtree root, sorted by id, into one outer digest.
+        // Each stream root is already a hashed digest (never raw leaf content), so it's
+        // combined with `build_levels_above` directly -- hashing it again as a leaf
+        // would add a layer `prove`'s witness-based proof could never account for.
+        let mut stream_ids: Vec<StreamID> = self.trees.keys().copied().collect();
+        stream_ids.sort_by_key(|id| id.0);
+        let mut stream_roots: Vec<MyDigest> = stream_ids.iter().map(|id| self.trees[id].root.clone()).collect();
+        if stream_roots.is_empty() {
+            stream_roots.push(self.empty_subtrees[self.depth].clone());
+        }
+        self.tree_levels = build_levels_above(stream_roots);
+        self.leaf_indices = stream_ids.iter().enumerate().map(|(index, id)| (*id, index)).collect();
+        MerkleTreeDigest(self.tree_levels.last().unwrap()[0].0.to_vec())
+    }
+}
+
+/// The depth of `SparseMerkleTreeNode`'s tree: one level per bit of a `StreamID`, so
+/// every possible stream has its own fixed leaf position.
+pub const SPARSE_TREE_DEPTH: usize = 32;
+
+/// Checks a `SparseMerkleTreeNode` proof against `root`. Pass `Some(leaf)` to check a
+/// membership proof that `stream_id` holds `leaf`, or `None` to check a
+/// non-membership proof that `stream_id` has never been written.
+pub fn verify_sparse(root: &MyDigest, leaf: Option<&Leaf>, proof: &MerkleProof) -> bool {
+    let mut digest = match leaf {
+        Some(leaf) => hash_leaf(&leaf.0),
+        None => MyDigest(EMPTY_NODE),
+    };
+    for (sibling, sibling_is_left) in &proof.siblings {
+        digest = if *sibling_is_left {
+            hash_pair(sibling, &digest)
+        } else {
+            hash_pair(&digest, sibling)
+        };
+    }
+    digest == *root
+}
+
+/// Places every `StreamID`'s leaf at the tree position given by the bits of the
+/// stream id itself, rather than at a position assigned by insertion order. A
+/// subtree with no non-empty leaf beneath it is never stored -- it folds in as the
+/// precomputed constant for its height -- so the tree only ever holds the O(depth)
+/// nodes on the path to each stream that's actually been written.
+pub struct SparseMerkleTreeNode<S: Store<MyDigest>> {
+    depth: usize,
+    empty_subtrees: Vec<MyDigest>,
+    store: S,
+    // nodes[&(level, index)] is the digest of the node at `index` within `level`
+    // (leaves are level 0), present only where that subtree has a non-empty leaf
+    // beneath it; every other position folds to `empty_subtrees[level]`.
+    nodes: HashMap<(usize, u32), MyDigest>,
+    root: MyDigest,
+}
+
+impl<S: Store<MyDigest>> SparseMerkleTreeNode<S> {
+    pub fn new(depth: usize, store: S) -> Self {
+        let empty_subtrees = build_empty_subtrees(depth);
+        let root = empty_subtrees[depth].clone();
+        Self {
+            depth,
+            empty_subtrees,
+            store,
+            nodes: HashMap::new(),
+            root,
+        }
+    }
+
+    /// The current chain head for `stream_id`, if it has ever been touched.
+    pub fn head(&self, stream_id: StreamID) -> Option<MyDigest> {
+        self.store.get(stream_id)
+    }
+
+    /// The tree's current global root.
+    pub fn root(&self) -> MyDigest {
+        self.root.clone()
+    }
+
+    /// Sets `stream_id`'s leaf to `leaf_digest` and recomputes just the O(depth)
+    /// nodes on its path to the root, leaving every other branch untouched.
+    fn set_leaf(&mut self, stream_id: StreamID, leaf_digest: MyDigest) {
+        let mut index = stream_id.0;
+        let mut node = leaf_digest;
+        self.nodes.insert((0, index), node.clone());
+        for level in 0..self.depth {
+            let sibling_index = index ^ 1;
+            let sibling = self
+                .nodes
+                .get(&(level, sibling_index))
+                .cloned()
+                .unwrap_or_else(|| self.empty_subtrees[level].clone());
+            node = if index % 2 == 0 {
+                hash_pair(&node, &sibling)
+            } else {
+                hash_pair(&sibling, &node)
+            };
+            index /= 2;
+            self.nodes.insert((level + 1, index), node.clone());
+        }
+        self.root = node;
+    }
+
+    /// A membership (or, for a stream that's never been written, non-membership)
+    /// proof of `depth` siblings against the current root.
+    pub fn prove(&self, stream_id: StreamID) -> MerkleProof {
+        let mut siblings = Vec::with_capacity(self.depth);
+        let mut index = stream_id.0;
+        for level in 0..self.depth {
+            let sibling_index = index ^ 1;
+            let sibling = self
+                .nodes
+                .get(&(level, sibling_index))
+                .cloned()
+                .unwrap_or_else(|| self.empty_subtrees[level].clone());
+            siblings.push((sibling, sibling_index < index));
+            index /= 2;
+        }
+        MerkleProof { leaf_index: stream_id.0 as usize, siblings }
+    }
+}
+
+impl<S: Store<MyDigest>> StreamUpdater for SparseMerkleTreeNode<S> {
+    // Every stream has a fixed leaf position in this tree, so it's always provable --
+    // present (membership) or absent (non-membership).
+    fn prove(&self, stream_id: StreamID) -> Option<MerkleProof> {
+        Some(self.prove(stream_id))
+    }
+
+    fn update(&mut self, updates: Vec<StreamUpdate>) -> MerkleTreeDigest {
+        for (stream_id, points) in updates {
+            // Fold every point in this batch into the stream's existing head, the
+            // same running hash chain `HashChainSparseNode` keeps.
+            let mut head = self.store.get(stream_id).unwrap_or(MyDigest(EMPTY_NODE));
+            for point in points {
+                let mut hasher = Sha256::new();
+                hasher.update([MERKLE_HASH_PREFIX_LEAF]);
+                hasher.update(&head.0);
+                hasher.update(&point.0);
+                head = MyDigest(hasher.finalize().into());
+            }
+            self.store.set(stream_id, head.clone());
+            self.set_leaf(stream_id, hash_leaf(&head.0));
+        }
+        MerkleTreeDigest(self.root.0.to_vec())
     }
 }
 
@@ -113,12 +1068,12 @@ fn main() {
         (StreamID(1), vec![EMPTY_POINT]),
     ];
 
-    let mut counters = CounterSparseNode::new();
+    let mut counters = CounterSparseNode::new(InMemoryStore::new());
     let digest1 = counters.update(updates);
     println!("Counters Digest: {:?}", digest1);
 
     // Print the final counters for debugging
-    println!("Final Counters: {:?}", counters.counts);
+    println!("Final count for stream 0: {:?}", counters.count(StreamID(0)));
 
     let updates2 = vec![
         (StreamID(0), vec![EMPTY_POINT]),
@@ -129,7 +1084,7 @@ fn main() {
     println!("Counters Digest: {:?}", digest2);
 
     // Print the final counters for debugging after the second update
-    println!("Final Counters after second update: {:?}", counters.counts);
+    println!("Final count for stream 0: {:?}", counters.count(StreamID(0)));
 }
 
 // Add tests here
@@ -146,7 +1101,7 @@ mod tests {
             (StreamID(1), vec![EMPTY_POINT]),
         ];
 
-        let mut counters = CounterSparseNode::new();
+        let mut counters = CounterSparseNode::new(InMemoryStore::new());
         let digest1 = counters.update(updates);
         assert_eq!(digest1.0.len(), DIGEST_LEN);
 
@@ -158,12 +1113,9 @@ mod tests {
         let digest2 = counters.update(updates2);
         assert_eq!(digest2.0.len(), DIGEST_LEN);
 
-        // Print the final counters for debugging after the second update
-        println!("Final Counters after second update: {:?}", counters.counts);
-        assert_eq!(counters.counts.len(), 3);
-        assert_eq!(counters.counts.get(&StreamID(0)).unwrap(), &3);
-        assert_eq!(counters.counts.get(&StreamID(1)).unwrap(), &1);
-        assert_eq!(counters.counts.get(&StreamID(2)).unwrap(), &2);
+        assert_eq!(counters.count(StreamID(0)).unwrap(), 3);
+        assert_eq!(counters.count(StreamID(1)).unwrap(), 1);
+        assert_eq!(counters.count(StreamID(2)).unwrap(), 2);
     }
 
     #[test]
@@ -173,10 +1125,11 @@ mod tests {
             (StreamID(1), vec![EMPTY_POINT]),
         ];
 
-        let mut hash_chain = HashChainSparseNode::new();
+        let mut hash_chain = HashChainSparseNode::new(InMemoryStore::new());
         let digest1 = hash_chain.update(updates);
         assert_eq!(digest1.0.len(), DIGEST_LEN);
-        assert_eq!(hash_chain.heads.len(), 2);
+        assert!(hash_chain.head(StreamID(0)).is_some());
+        assert!(hash_chain.head(StreamID(1)).is_some());
 
         let updates2 = vec![
             (StreamID(0), vec![EMPTY_POINT]),
@@ -184,30 +1137,31 @@ mod tests {
         ];
 
         let digest2 = hash_chain.update(updates2);
-
-        // Print the final hash chain for debugging after the second update
-        println!("Final Hash Chain after second update: {:?}", hash_chain.heads);
         assert_eq!(digest2.0.len(), DIGEST_LEN);
-        assert_eq!(hash_chain.heads.len(), 3);
-        
+
         // Check the final hash chain
-        let head0 = hash_chain.heads.get(&StreamID(0)).unwrap();
-        let head1 = hash_chain.heads.get(&StreamID(1)).unwrap();
-        let head2 = hash_chain.heads.get(&StreamID(2)).unwrap();
+        let head0 = hash_chain.head(StreamID(0)).unwrap();
+        let head1 = hash_chain.head(StreamID(1)).unwrap();
+        let head2 = hash_chain.head(StreamID(2)).unwrap();
 
-        // Compute the hash chain as H(EMPTY_POINT) -> H(H(EMPTY_POINT)) -> H(H(H(EMPTY_POINT)))
+        // Compute the hash chain as H(00|EMPTY_POINT|EMPTY_POINT) -> H(00|prev|EMPTY_POINT) -> ...
+        // starting from an all-zero head, where the leading 00 is MERKLE_HASH_PREFIX_LEAF,
+        // tagging every chain step.
         let mut hasher = Sha256::new();
+        hasher.update([MERKLE_HASH_PREFIX_LEAF]);
         hasher.update(&EMPTY_POINT.0); // We always start with the empty point
         hasher.update(&EMPTY_POINT.0); // First update
         let digest = hasher.finalize();
         assert_eq!(head1.0, digest.as_slice());
 
         let mut hasher = Sha256::new();
+        hasher.update([MERKLE_HASH_PREFIX_LEAF]);
         hasher.update(digest.as_slice());
         hasher.update(&EMPTY_POINT.0); // Second update
         let digest = hasher.finalize();
 
         let mut hasher = Sha256::new();
+        hasher.update([MERKLE_HASH_PREFIX_LEAF]);
         hasher.update(digest.as_slice());
         hasher.update(&EMPTY_POINT.0); // Third update
         let digest = hasher.finalize();
@@ -215,5 +1169,266 @@ mod tests {
         assert_eq!(head2.0, digest.as_slice());
         assert_eq!(head0, head2);
     }
+
+    #[test]
+    fn test_counter_sparse_node_merkle_proof() {
+        let updates = vec![
+            (StreamID(0), vec![EMPTY_POINT, EMPTY_POINT]),
+            (StreamID(1), vec![EMPTY_POINT]),
+            (StreamID(2), vec![EMPTY_POINT, EMPTY_POINT, EMPTY_POINT]),
+        ];
+
+        let mut counters = CounterSparseNode::new(InMemoryStore::new());
+        let digest = counters.update(updates);
+        let root = MyDigest(digest.0.clone().try_into().unwrap());
+
+        for stream_id in [StreamID(0), StreamID(1), StreamID(2)] {
+            let proof = counters.prove(stream_id).unwrap();
+            let counter = counters.count(stream_id).unwrap();
+            let delta = counters.last_deltas[&stream_id];
+            let leaf = CounterSparseNode::<InMemoryStore<u32>>::leaf_bytes(stream_id, delta, counter);
+            assert!(verify(&root, &leaf, &proof));
+        }
+
+        assert!(counters.prove(StreamID(99)).is_none());
+    }
+
+    #[test]
+    fn test_hash_chain_sparse_node_merkle_proof() {
+        let updates = vec![
+            (StreamID(0), vec![EMPTY_POINT, EMPTY_POINT]),
+            (StreamID(1), vec![EMPTY_POINT]),
+        ];
+
+        let mut hash_chain = HashChainSparseNode::new(InMemoryStore::new());
+        let digest = hash_chain.update(updates);
+        let root = MyDigest(digest.0.clone().try_into().unwrap());
+
+        for stream_id in [StreamID(0), StreamID(1)] {
+            let proof = hash_chain.prove(stream_id).unwrap();
+            let leaf = Leaf(hash_chain.head(stream_id).unwrap().0.to_vec());
+            assert!(verify(&root, &leaf, &proof));
+        }
+    }
+
+    #[test]
+    fn test_incremental_sparse_node_witness_stays_current() {
+        const DEPTH: usize = 4;
+        let points: Vec<Point> = (0..5u8).map(|i| Point([i; DIGEST_LEN])).collect();
+        let witnessed_leaf = Leaf(points[0].0.to_vec());
+
+        let mut node = IncrementalSparseNode::new(DEPTH);
+        node.update(vec![(StreamID(0), vec![Point(points[0].0)])]);
+
+        let witness = node.witness(StreamID(0)).unwrap();
+        assert_eq!(witness.leaf_index, 0);
+
+        // Append the remaining points one batch at a time; the witness should stay
+        // valid against the stream's root after every single update.
+        for point in &points[1..] {
+            node.update(vec![(StreamID(0), vec![Point(point.0)])]);
+            let root = node.root(StreamID(0)).unwrap();
+            let witness = node.get_witness(StreamID(0)).unwrap();
+            assert!(verify_witness(&root, &witnessed_leaf, &witness, DEPTH));
+        }
+    }
+
+    #[test]
+    fn test_incremental_sparse_node_witness_on_non_first_leaf() {
+        const DEPTH: usize = 4;
+        let points: Vec<Point> = (0..4u8).map(|i| Point([i; DIGEST_LEN])).collect();
+        let witnessed_leaf = Leaf(points[2].0.to_vec());
+
+        let mut node = IncrementalSparseNode::new(DEPTH);
+        // Leaves 0 and 1 close off before leaf 2 is witnessed, so its level-1
+        // sibling is already a closed pair hash rather than the empty constant.
+        node.update(vec![(StreamID(0), points[..3].iter().map(|p| Point(p.0)).collect())]);
+
+        let witness = node.witness(StreamID(0)).unwrap();
+        assert_eq!(witness.leaf_index, 2);
+        let root = node.root(StreamID(0)).unwrap();
+        assert!(verify_witness(&root, &witnessed_leaf, &witness, DEPTH));
+
+        // The witness must still track leaf 2 correctly once leaf 3 closes the pair.
+        node.update(vec![(StreamID(0), vec![Point(points[3].0)])]);
+        let root = node.root(StreamID(0)).unwrap();
+        let witness = node.get_witness(StreamID(0)).unwrap();
+        assert!(verify_witness(&root, &witnessed_leaf, &witness, DEPTH));
+    }
+
+    #[test]
+    fn test_incremental_sparse_node_prove_matches_digest_returned_by_update() {
+        const DEPTH: usize = 4;
+        let point = Point([7; DIGEST_LEN]);
+        let leaf = Leaf(point.0.to_vec());
+
+        let mut node = IncrementalSparseNode::new(DEPTH);
+        let digest = node.update(vec![(StreamID(0), vec![Point(point.0)])]);
+        node.witness(StreamID(0));
+
+        let root = MyDigest(digest.0.try_into().unwrap());
+        let proof = node.prove(StreamID(0)).unwrap();
+        assert!(verify(&root, &leaf, &proof));
+    }
+
+    #[test]
+    fn test_sparse_merkle_tree_node_membership_and_non_membership() {
+        const DEPTH: usize = 8;
+        let updates = vec![
+            (StreamID(3), vec![EMPTY_POINT]),
+            (StreamID(200), vec![EMPTY_POINT, EMPTY_POINT]),
+        ];
+
+        let mut node = SparseMerkleTreeNode::new(DEPTH, InMemoryStore::new());
+        let digest = node.update(updates);
+        let root = MyDigest(digest.0.try_into().unwrap());
+        assert_eq!(root, node.root());
+
+        for stream_id in [StreamID(3), StreamID(200)] {
+            let proof = node.prove(stream_id);
+            let leaf = Leaf(node.head(stream_id).unwrap().0.to_vec());
+            assert!(verify_sparse(&root, Some(&leaf), &proof));
+        }
+
+        // A stream that was never written proves as absent against the same root.
+        let untouched = StreamID(42);
+        assert!(node.head(untouched).is_none());
+        let proof = node.prove(untouched);
+        assert!(verify_sparse(&root, None, &proof));
+    }
+
+    #[test]
+    fn test_apply_batches_reads_against_pre_batch_state_and_writes_against_post_batch_state() {
+        const DEPTH: usize = 8;
+        let mut node = SparseMerkleTreeNode::new(DEPTH, InMemoryStore::new());
+        node.update(vec![(StreamID(3), vec![EMPTY_POINT])]);
+        let pre_batch_root = node.root();
+        let pre_batch_leaf = Leaf(node.head(StreamID(3)).unwrap().0.to_vec());
+
+        // Stream 7 shares stream 3's sibling at a shallow level of this depth-8
+        // tree, so writing it in the same batch changes the digest along stream 3's
+        // own path -- the read proof must still check out against the root from
+        // before this batch, not the one `apply` returns for after it.
+        let instructions = vec![
+            TreeInstruction::Read(StreamID(3)),
+            TreeInstruction::Write(StreamID(3), vec![EMPTY_POINT]),
+            TreeInstruction::Write(StreamID(7), vec![EMPTY_POINT]),
+        ];
+        let (digest, proofs) = node.apply(instructions);
+        let root = MyDigest(digest.0.try_into().unwrap());
+        assert_eq!(root, node.root());
+        assert_eq!(proofs.len(), 3);
+
+        // The read is proved against the state before this batch's writes landed.
+        assert!(verify_sparse(&pre_batch_root, Some(&pre_batch_leaf), proofs[0].as_ref().unwrap()));
+
+        // Both writes are proved against the state after the whole batch applied.
+        let post_batch_leaf_3 = Leaf(node.head(StreamID(3)).unwrap().0.to_vec());
+        let post_batch_leaf_7 = Leaf(node.head(StreamID(7)).unwrap().0.to_vec());
+        assert!(verify_sparse(&root, Some(&post_batch_leaf_3), proofs[1].as_ref().unwrap()));
+        assert!(verify_sparse(&root, Some(&post_batch_leaf_7), proofs[2].as_ref().unwrap()));
+    }
+
+    #[test]
+    fn test_apply_returns_none_for_a_read_of_an_untouched_stream() {
+        // CounterSparseNode and HashChainSparseNode don't support non-membership
+        // proofs, so `apply` must hand back `None` for an untouched stream's `Read`
+        // instead of panicking.
+        let mut counters = CounterSparseNode::new(InMemoryStore::new());
+        let (_, proofs) = counters.apply(vec![
+            TreeInstruction::Read(StreamID(0)),
+            TreeInstruction::Write(StreamID(0), vec![EMPTY_POINT]),
+        ]);
+        assert!(proofs[0].is_none());
+        assert!(proofs[1].is_some());
+
+        let mut hash_chain = HashChainSparseNode::new(InMemoryStore::new());
+        let (_, proofs) = hash_chain.apply(vec![
+            TreeInstruction::Read(StreamID(0)),
+            TreeInstruction::Write(StreamID(0), vec![EMPTY_POINT]),
+        ]);
+        assert!(proofs[0].is_none());
+        assert!(proofs[1].is_some());
+    }
+
+    #[test]
+    fn test_counter_sparse_node_transaction_commit_and_rollback() {
+        let mut counters = CounterSparseNode::new(InMemoryStore::new());
+        let base_digest = counters.update(vec![(StreamID(0), vec![EMPTY_POINT])]);
+        assert_eq!(counters.count(StreamID(0)).unwrap(), 1);
+
+        // A rolled-back transaction leaves the node exactly as it found it.
+        counters.begin();
+        let tentative_digest =
+            counters.update(vec![(StreamID(0), vec![EMPTY_POINT]), (StreamID(1), vec![EMPTY_POINT])]);
+        assert_eq!(tentative_digest.0, counters.root().0);
+        assert_ne!(tentative_digest.0, base_digest.0);
+        assert_eq!(counters.count(StreamID(0)).unwrap(), 2);
+        assert_eq!(counters.count(StreamID(1)).unwrap(), 1);
+
+        counters.rollback();
+        assert_eq!(counters.count(StreamID(0)).unwrap(), 1);
+        assert_eq!(counters.count(StreamID(1)), None);
+        assert_eq!(counters.update(Vec::new()).0, base_digest.0);
+
+        // A committed transaction writes through, so the root matches one computed
+        // by re-executing the same batch without ever opening a transaction.
+        counters.begin();
+        let committed_digest = counters.update(vec![(StreamID(1), vec![EMPTY_POINT, EMPTY_POINT])]);
+        counters.commit();
+        assert_eq!(counters.count(StreamID(1)).unwrap(), 2);
+
+        let mut replay = CounterSparseNode::new(InMemoryStore::new());
+        replay.update(vec![(StreamID(0), vec![EMPTY_POINT])]);
+        let replay_digest = replay.update(vec![(StreamID(1), vec![EMPTY_POINT, EMPTY_POINT])]);
+        assert_eq!(committed_digest.0, replay_digest.0);
+    }
+
+    #[test]
+    fn test_hash_chain_sparse_node_transaction_commit_and_rollback() {
+        let mut hash_chain = HashChainSparseNode::new(InMemoryStore::new());
+        hash_chain.update(vec![(StreamID(0), vec![EMPTY_POINT])]);
+        let base_head = hash_chain.head(StreamID(0)).unwrap();
+
+        // A rolled-back transaction leaves the node exactly as it found it.
+        hash_chain.begin();
+        hash_chain.update(vec![(StreamID(0), vec![EMPTY_POINT]), (StreamID(1), vec![EMPTY_POINT])]);
+        assert_ne!(hash_chain.head(StreamID(0)).unwrap(), base_head);
+        assert!(hash_chain.head(StreamID(1)).is_some());
+
+        hash_chain.rollback();
+        assert_eq!(hash_chain.head(StreamID(0)).unwrap(), base_head);
+        assert_eq!(hash_chain.head(StreamID(1)), None);
+
+        // A committed transaction writes through, so the root matches one computed
+        // by re-executing the same batch without ever opening a transaction.
+        hash_chain.begin();
+        let committed_digest = hash_chain.update(vec![(StreamID(1), vec![EMPTY_POINT])]);
+        hash_chain.commit();
+        assert!(hash_chain.head(StreamID(1)).is_some());
+
+        let mut replay = HashChainSparseNode::new(InMemoryStore::new());
+        replay.update(vec![(StreamID(0), vec![EMPTY_POINT])]);
+        let replay_digest = replay.update(vec![(StreamID(1), vec![EMPTY_POINT])]);
+        assert_eq!(committed_digest.0, replay_digest.0);
+    }
+
+    #[test]
+    #[cfg(feature = "rocksdb")]
+    fn test_rocksdb_store_survives_reopen() {
+        let dir = std::env::temp_dir().join(format!("sparse-nodes-rocksdb-smoke-{:?}", std::thread::current().id()));
+        {
+            let mut store: RocksDbStore<u32> = RocksDbStore::open(&dir).unwrap();
+            store.set(StreamID(0), 7);
+            assert_eq!(store.get(StreamID(0)), Some(7));
+            assert_eq!(store.iter_updated(), vec![(StreamID(0), 7)]);
+        }
+
+        // Reopening the same path must see the value the first handle persisted.
+        let store: RocksDbStore<u32> = RocksDbStore::open(&dir).unwrap();
+        assert_eq!(store.get(StreamID(0)), Some(7));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
 }
  